@@ -0,0 +1,80 @@
+//! `--condition` filtering: a CEL expression evaluated once per [`Entry`],
+//! mirroring flake-checker's CEL-based policy checks.
+
+use crate::Entry;
+use cel_interpreter::{Context, Program, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Ref types this crate understands, exposed to conditions as `supportedRefs`.
+const SUPPORTED_REFS: &[&str] = &[
+    "github", "gitlab", "sourcehut", "git", "tarball", "path", "indirect",
+];
+
+/// A compiled `--condition` expression.
+pub struct Condition {
+    program: Program,
+}
+
+impl Condition {
+    pub fn compile(expr: &str) -> Result<Self, String> {
+        let program =
+            Program::compile(expr).map_err(|e| format!("invalid --condition expression: {e}"))?;
+        Ok(Condition { program })
+    }
+
+    /// Whether `entry` should be kept. `owner`/`repo` are empty strings for ref
+    /// types that don't carry them, `refType` is empty for an entry with no ref
+    /// (a `follows` add), and `numDaysOld` is `0` for an entry with no date.
+    pub fn matches(&self, entry: &Entry) -> Result<bool, String> {
+        let flake_ref = entry.flake_ref();
+
+        let mut ctx = Context::default();
+        ctx.add_variable("owner", flake_ref.map(|f| f.owner()).unwrap_or(""))
+            .map_err(|e| e.to_string())?;
+        ctx.add_variable("repo", flake_ref.map(|f| f.repo()).unwrap_or(""))
+            .map_err(|e| e.to_string())?;
+        ctx.add_variable(
+            "refType",
+            flake_ref.map(|f| f.type_name()).unwrap_or(""),
+        )
+        .map_err(|e| e.to_string())?;
+        ctx.add_variable("isUpdate", matches!(entry, Entry::Updated(..)))
+            .map_err(|e| e.to_string())?;
+        ctx.add_variable("isAdd", matches!(entry, Entry::Added(..)))
+            .map_err(|e| e.to_string())?;
+        ctx.add_variable("numDaysOld", num_days_old(entry))
+            .map_err(|e| e.to_string())?;
+        ctx.add_variable(
+            "supportedRefs",
+            SUPPORTED_REFS
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .map_err(|e| e.to_string())?;
+
+        match self.program.execute(&ctx) {
+            Ok(Value::Bool(matched)) => Ok(matched),
+            Ok(_) => Err("--condition expression must evaluate to a bool".to_string()),
+            Err(e) => Err(format!("failed to evaluate --condition: {e}")),
+        }
+    }
+}
+
+/// Age in days of `entry`'s `to` date, relative to now. `0` when the entry
+/// carries no date (e.g. a `follows` add).
+fn num_days_old(entry: &Entry) -> i64 {
+    let Some(date) = entry.date() else {
+        return 0;
+    };
+    let Some((y, m, d)) = crate::parse_date(date) else {
+        return 0;
+    };
+
+    let today_days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64 / 86400)
+        .unwrap_or(0);
+
+    today_days - crate::days_from_civil(y, m, d)
+}
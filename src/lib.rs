@@ -4,85 +4,365 @@ use nom::{
     bytes::complete::{tag, take_until, take_while1},
     character::complete::{char, line_ending, not_line_ending, space0, space1},
     combinator::{opt, verify},
-    sequence::delimited,
+    sequence::{delimited, preceded},
 };
-
-#[derive(Debug, PartialEq)]
-enum FlakeRefType {
-    Github,
-    Gitlab,
+use std::borrow::Cow;
+
+mod condition;
+mod lock;
+mod tree;
+pub use condition::Condition;
+pub use lock::{LockFile, diff as diff_lock_files};
+pub use tree::render as render_tree;
+
+/// Parse a `YYYY-MM-DD` date into `(year, month, day)`. Returns `None` on anything
+/// that doesn't look like a well-formed date rather than panicking.
+pub(crate) fn parse_date(s: &str) -> Option<(i32, i32, i32)> {
+    let mut parts = s.splitn(3, '-');
+    let y = parts.next()?.parse().ok()?;
+    let m = parts.next()?.parse().ok()?;
+    let d = parts.next()?.parse().ok()?;
+    Some((y, m, d))
 }
 
-impl<'a> TryFrom<&'a str> for FlakeRefType {
-    type Error = nom::Err<nom::error::Error<&'a str>>;
-
-    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
-        match value {
-            "github" => Ok(FlakeRefType::Github),
-            "gitlab" => Ok(FlakeRefType::Gitlab),
-            _ => Err(nom::Err::Error(nom::error::Error::new(
-                value,
-                nom::error::ErrorKind::Tag,
-            ))),
-        }
-    }
+/// Days since the Unix epoch for a civil (Gregorian) date, per Howard Hinnant's
+/// `days_from_civil` algorithm: <http://howardhinnant.github.io/date_algorithms.html>.
+pub(crate) fn days_from_civil(y: i32, m: i32, d: i32) -> i64 {
+    let y = (if m <= 2 { y - 1 } else { y }) as i64;
+    let (m, d) = (m as i64, d as i64);
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400; // [0, 399]
+    let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + d - 1; // [0, 365]
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy; // [0, 146096]
+    era * 146097 + doe - 719468
 }
 
+/// A forge-hosted ref: `owner/repo/rev`, e.g. `nix-community/home-manager/bd92e8e...`
+/// or sourcehut's `~user/repo/rev` (the `~` is part of `owner`).
 #[derive(Debug, PartialEq)]
-struct FlakeRef<'a> {
-    ref_type: FlakeRefType,
+pub(crate) struct ForgeRef<'a> {
+    owner: &'a str,
     repo: &'a str,
     commit: &'a str,
+    /// `?host=` override, e.g. a self-hosted GitHub/GitLab instance.
+    host: Option<&'a str>,
+    /// `?dir=` subdirectory the flake lives in.
+    dir: Option<&'a str>,
 }
 
-impl<'a> FlakeRef<'a> {
-    /// Parse a flake ref from the input. Query parameters in the url are ignored.
-    fn parse_from(input: &'a str) -> IResult<&'a str, Self> {
-        let (input, ref_type_str) = take_until(":")(input)?;
-        let (input, _) = char(':')(input)?;
-        let ref_type = ref_type_str.try_into()?;
+impl<'a> ForgeRef<'a> {
+    pub(crate) fn new(owner: &'a str, repo: &'a str, commit: &'a str) -> Self {
+        ForgeRef {
+            owner,
+            repo,
+            commit,
+            host: None,
+            dir: None,
+        }
+    }
 
-        let (input, repo_and_sha) =
+    fn parse_from(input: &'a str) -> IResult<&'a str, Self> {
+        let (input, owner_repo_commit) =
             verify(take_while1(|c: char| c != '?' && c != '\n'), |s: &str| {
                 s.matches('/').count() == 2
             })
             .parse(input)?;
-        let (input, _) = opt(|i| {
-            let (i, _) = char('?')(i)?;
-            not_line_ending(i)
-        })
-        .parse(input)?;
 
-        let parts: Vec<&str> = repo_and_sha.rsplitn(2, '/').collect();
+        let parts: Vec<&str> = owner_repo_commit.splitn(3, '/').collect();
         Ok((
             input,
-            FlakeRef {
-                ref_type,
+            ForgeRef {
+                owner: parts[0],
                 repo: parts[1],
-                commit: parts[0],
+                commit: parts[2],
+                host: None,
+                dir: None,
             },
         ))
     }
+}
 
-    fn repo_url(&self) -> String {
-        match self.ref_type {
-            FlakeRefType::Github => format!("https://github.com/{}", self.repo),
-            FlakeRefType::Gitlab => format!("https://gitlab.com/{}", self.repo),
+/// A ref that carries a full URL rather than an `owner/repo` pair, e.g.
+/// `git+https://example.com/foo.git` or `tarball+https://example.com/foo.tar.gz`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct UrlRef<'a> {
+    url: &'a str,
+    /// The `rev` a `flake.lock` node locks to, or a text ref's `?rev=` override.
+    /// `git`/`tarball` refs have no owner/repo/commit triple of their own, so
+    /// this is what `sha()` shows instead of repeating the URL.
+    rev: Option<&'a str>,
+    dir: Option<&'a str>,
+}
+
+impl<'a> UrlRef<'a> {
+    pub(crate) fn new(url: &'a str) -> Self {
+        UrlRef {
+            url,
+            rev: None,
+            dir: None,
+        }
+    }
+
+    pub(crate) fn with_rev(url: &'a str, rev: &'a str) -> Self {
+        UrlRef {
+            url,
+            rev: Some(rev),
+            dir: None,
+        }
+    }
+
+    fn parse_from(input: &'a str) -> IResult<&'a str, Self> {
+        let (input, url) = take_while1(|c: char| c != '?' && c != '\n')(input)?;
+        Ok((
+            input,
+            UrlRef {
+                url,
+                rev: None,
+                dir: None,
+            },
+        ))
+    }
+}
+
+/// A local `path:` ref.
+#[derive(Debug, PartialEq)]
+pub(crate) struct PathRef<'a> {
+    path: &'a str,
+    dir: Option<&'a str>,
+}
+
+impl<'a> PathRef<'a> {
+    pub(crate) fn new(path: &'a str) -> Self {
+        PathRef { path, dir: None }
+    }
+
+    fn parse_from(input: &'a str) -> IResult<&'a str, Self> {
+        let (input, path) = take_while1(|c: char| c != '?' && c != '\n')(input)?;
+        Ok((input, PathRef { path, dir: None }))
+    }
+}
+
+/// A registry/indirect ref, e.g. `indirect:nixpkgs` or the bare shorthand `nixpkgs`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct IndirectRef<'a> {
+    id: &'a str,
+    dir: Option<&'a str>,
+}
+
+impl<'a> IndirectRef<'a> {
+    pub(crate) fn new(id: &'a str) -> Self {
+        IndirectRef { id, dir: None }
+    }
+
+    fn parse_from(input: &'a str) -> IResult<&'a str, Self> {
+        let (input, id) = take_while1(|c: char| c != '?' && c != '\n')(input)?;
+        Ok((input, IndirectRef { id, dir: None }))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum FlakeRef<'a> {
+    Github(ForgeRef<'a>),
+    Gitlab(ForgeRef<'a>),
+    Sourcehut(ForgeRef<'a>),
+    Git(UrlRef<'a>),
+    Tarball(UrlRef<'a>),
+    Path(PathRef<'a>),
+    Indirect(IndirectRef<'a>),
+}
+
+impl<'a> FlakeRef<'a> {
+    /// Parse a flake ref from the input. `?host=`, `?dir=` and `?rev=` are honored
+    /// (see [`Self::apply_query`]); other query keys are ignored.
+    fn parse_from(input: &'a str) -> IResult<&'a str, Self> {
+        let (rest, mut flake_ref) = match take_until::<_, _, nom::error::Error<&'a str>>(":")
+            .parse(input)
+        {
+            Ok((rest, scheme)) => {
+                let (rest, _) = char(':')(rest)?;
+                match scheme {
+                    "github" => ForgeRef::parse_from(rest).map(|(i, f)| (i, FlakeRef::Github(f))),
+                    "gitlab" => ForgeRef::parse_from(rest).map(|(i, f)| (i, FlakeRef::Gitlab(f))),
+                    "sourcehut" => {
+                        ForgeRef::parse_from(rest).map(|(i, f)| (i, FlakeRef::Sourcehut(f)))
+                    }
+                    "path" => PathRef::parse_from(rest).map(|(i, p)| (i, FlakeRef::Path(p))),
+                    "indirect" => {
+                        IndirectRef::parse_from(rest).map(|(i, r)| (i, FlakeRef::Indirect(r)))
+                    }
+                    // `git+`/`tarball+` only strip the fetcher prefix; the transport
+                    // scheme (e.g. `https:`) is part of the URL, not our delimiter, so
+                    // we re-slice from `input` rather than use the colon-split `rest`.
+                    s if s.starts_with("git+") => {
+                        UrlRef::parse_from(&input["git+".len()..])
+                            .map(|(i, u)| (i, FlakeRef::Git(u)))
+                    }
+                    s if s.starts_with("tarball+") => {
+                        UrlRef::parse_from(&input["tarball+".len()..])
+                            .map(|(i, u)| (i, FlakeRef::Tarball(u)))
+                    }
+                    _ => Err(nom::Err::Error(nom::error::Error::new(
+                        input,
+                        nom::error::ErrorKind::Tag,
+                    ))),
+                }
+            }
+            // No `:` at all: a bare registry shorthand like `nixpkgs`.
+            Err(_) => IndirectRef::parse_from(input).map(|(i, r)| (i, FlakeRef::Indirect(r))),
+        }?;
+
+        let (rest, query) = opt(preceded(char('?'), not_line_ending)).parse(rest)?;
+        if let Some(query) = query {
+            flake_ref.apply_query(query);
+        }
+
+        Ok((rest, flake_ref))
+    }
+
+    /// Apply `key=value&...` query parameters. `host` redirects a github/gitlab
+    /// ref to a self-hosted instance; `dir` records the subdirectory the flake
+    /// lives in; `rev` records the revision a `git`/`tarball` ref locks to.
+    /// Unknown keys are ignored.
+    fn apply_query(&mut self, query: &'a str) {
+        for pair in query.split('&') {
+            let mut kv = pair.splitn(2, '=');
+            let key = kv.next().unwrap_or("");
+            let Some(value) = kv.next() else { continue };
+            match key {
+                "host" => self.set_host(value),
+                "dir" => self.set_dir(value),
+                "rev" => self.set_rev(value),
+                _ => {}
+            }
+        }
+    }
+
+    fn set_host(&mut self, host: &'a str) {
+        match self {
+            FlakeRef::Github(f) | FlakeRef::Gitlab(f) => f.host = Some(host),
+            _ => {}
+        }
+    }
+
+    fn set_rev(&mut self, rev: &'a str) {
+        match self {
+            FlakeRef::Git(u) | FlakeRef::Tarball(u) => u.rev = Some(rev),
+            _ => {}
+        }
+    }
+
+    fn set_dir(&mut self, dir: &'a str) {
+        match self {
+            FlakeRef::Github(f) | FlakeRef::Gitlab(f) | FlakeRef::Sourcehut(f) => {
+                f.dir = Some(dir)
+            }
+            FlakeRef::Git(u) | FlakeRef::Tarball(u) => u.dir = Some(dir),
+            FlakeRef::Path(p) => p.dir = Some(dir),
+            FlakeRef::Indirect(r) => r.dir = Some(dir),
+        }
+    }
+
+    /// The `?dir=` subdirectory the flake lives in, if any.
+    pub(crate) fn dir(&self) -> Option<&'a str> {
+        match self {
+            FlakeRef::Github(f) | FlakeRef::Gitlab(f) | FlakeRef::Sourcehut(f) => f.dir,
+            FlakeRef::Git(u) | FlakeRef::Tarball(u) => u.dir,
+            FlakeRef::Path(p) => p.dir,
+            FlakeRef::Indirect(r) => r.dir,
+        }
+    }
+
+    fn repo_url(&self) -> Option<String> {
+        match self {
+            FlakeRef::Github(f) => {
+                Some(format!("https://{}/{}/{}", f.host.unwrap_or("github.com"), f.owner, f.repo))
+            }
+            FlakeRef::Gitlab(f) => {
+                Some(format!("https://{}/{}/{}", f.host.unwrap_or("gitlab.com"), f.owner, f.repo))
+            }
+            FlakeRef::Sourcehut(f) => Some(format!("https://git.sr.ht/{}/{}", f.owner, f.repo)),
+            FlakeRef::Git(u) | FlakeRef::Tarball(u) => Some(u.url.to_string()),
+            FlakeRef::Path(_) | FlakeRef::Indirect(_) => None,
         }
     }
 
     fn sha(&self) -> String {
-        self.commit[..8].to_string()
+        match self {
+            FlakeRef::Github(f) | FlakeRef::Gitlab(f) | FlakeRef::Sourcehut(f) => {
+                f.commit.get(..8).unwrap_or(f.commit).to_string()
+            }
+            FlakeRef::Git(u) | FlakeRef::Tarball(u) => match u.rev {
+                Some(rev) => rev.get(..8).unwrap_or(rev).to_string(),
+                None => u.url.to_string(),
+            },
+            FlakeRef::Path(p) => p.path.to_string(),
+            FlakeRef::Indirect(r) => r.id.to_string(),
+        }
+    }
+
+    /// A link from `self` to `other`, e.g. a forge's compare view between two revs.
+    /// Returns `None` when the ref type doesn't support one, or the two refs don't
+    /// point at the same repo.
+    fn compare_url(&self, other: &FlakeRef<'a>) -> Option<String> {
+        match (self, other) {
+            (FlakeRef::Github(f), FlakeRef::Github(t)) if f.owner == t.owner && f.repo == t.repo => {
+                Some(format!("{}/compare/{}...{}", self.repo_url()?, f.commit, t.commit))
+            }
+            (FlakeRef::Gitlab(f), FlakeRef::Gitlab(t)) if f.owner == t.owner && f.repo == t.repo => {
+                Some(format!("{}/-/compare/{}...{}", self.repo_url()?, f.commit, t.commit))
+            }
+            (FlakeRef::Sourcehut(f), FlakeRef::Sourcehut(t))
+                if f.owner == t.owner && f.repo == t.repo =>
+            {
+                Some(format!("{}/log/{}..{}", self.repo_url()?, f.commit, t.commit))
+            }
+            _ => None,
+        }
+    }
+
+    /// The ref type as CEL conditions see it via `refType`.
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            FlakeRef::Github(_) => "github",
+            FlakeRef::Gitlab(_) => "gitlab",
+            FlakeRef::Sourcehut(_) => "sourcehut",
+            FlakeRef::Git(_) => "git",
+            FlakeRef::Tarball(_) => "tarball",
+            FlakeRef::Path(_) => "path",
+            FlakeRef::Indirect(_) => "indirect",
+        }
+    }
+
+    pub(crate) fn owner(&self) -> &'a str {
+        match self {
+            FlakeRef::Github(f) | FlakeRef::Gitlab(f) | FlakeRef::Sourcehut(f) => f.owner,
+            FlakeRef::Git(_) | FlakeRef::Tarball(_) | FlakeRef::Path(_) | FlakeRef::Indirect(_) => {
+                ""
+            }
+        }
+    }
+
+    pub(crate) fn repo(&self) -> &'a str {
+        match self {
+            FlakeRef::Github(f) | FlakeRef::Gitlab(f) | FlakeRef::Sourcehut(f) => f.repo,
+            FlakeRef::Git(_) | FlakeRef::Tarball(_) | FlakeRef::Path(_) | FlakeRef::Indirect(_) => {
+                ""
+            }
+        }
     }
 }
 
 #[derive(Debug)]
 pub struct DatedFlakeRef<'a> {
     flake_ref: FlakeRef<'a>,
-    date: &'a str,
+    date: Cow<'a, str>,
 }
 
 impl<'a> DatedFlakeRef<'a> {
+    pub(crate) fn new(flake_ref: FlakeRef<'a>, date: Cow<'a, str>) -> Self {
+        DatedFlakeRef { flake_ref, date }
+    }
+
     fn parse_from(input: &'a str) -> IResult<&'a str, Self> {
         let (input, _) = space0(input)?;
         let (input, url) = delimited(tag("'"), take_until("'"), tag("'")).parse(input)?;
@@ -92,7 +372,13 @@ impl<'a> DatedFlakeRef<'a> {
 
         let (_, flake_ref) = FlakeRef::parse_from(url)?;
 
-        Ok((input, DatedFlakeRef { flake_ref, date }))
+        Ok((
+            input,
+            DatedFlakeRef {
+                flake_ref,
+                date: Cow::Borrowed(date),
+            },
+        ))
     }
 }
 
@@ -103,6 +389,10 @@ pub struct UpdateInfo<'a> {
 }
 
 impl<'a> UpdateInfo<'a> {
+    pub(crate) fn new(from: DatedFlakeRef<'a>, to: DatedFlakeRef<'a>) -> Self {
+        UpdateInfo { from, to }
+    }
+
     fn parse_from(input: &'a str) -> IResult<&'a str, Self> {
         let (input, from) = DatedFlakeRef::parse_from(input)?;
         let (input, _) = space0(input)?;
@@ -113,25 +403,21 @@ impl<'a> UpdateInfo<'a> {
     }
 
     fn url(&self) -> Option<String> {
-        let from = &self.from.flake_ref;
-        let to = &self.to.flake_ref;
+        self.from.flake_ref.compare_url(&self.to.flake_ref)
+    }
 
-        if from.repo != to.repo || from.ref_type != to.ref_type {
-            return None;
-        }
+    pub(crate) fn to_ref(&self) -> &FlakeRef<'a> {
+        &self.to.flake_ref
+    }
 
-        Some(format!(
-            "{}/compare/{}...{}",
-            from.repo_url(),
-            from.commit,
-            to.commit
-        ))
+    pub(crate) fn to_date(&self) -> &str {
+        &self.to.date
     }
 }
 
 #[derive(Debug)]
 pub enum AddInfo<'a> {
-    Follows(&'a str),
+    Follows(Cow<'a, str>),
     New(DatedFlakeRef<'a>),
 }
 
@@ -143,7 +429,7 @@ impl<'a> AddInfo<'a> {
                 let (i, _) = tag("follows ")(i)?;
                 let (i, repo) = delimited(tag("'"), take_until("'"), tag("'")).parse(i)?;
                 let (i, _) = line_ending(i)?;
-                Ok((i, AddInfo::Follows(repo)))
+                Ok((i, AddInfo::Follows(Cow::Borrowed(repo))))
             },
             |i| {
                 let (i, flake_ref) = DatedFlakeRef::parse_from(i)?;
@@ -152,40 +438,128 @@ impl<'a> AddInfo<'a> {
         ))
         .parse(input)
     }
+
+    pub(crate) fn flake_ref(&self) -> Option<&FlakeRef<'a>> {
+        match self {
+            AddInfo::Follows(_) => None,
+            AddInfo::New(dated_ref) => Some(&dated_ref.flake_ref),
+        }
+    }
+
+    pub(crate) fn date(&self) -> Option<&str> {
+        match self {
+            AddInfo::Follows(_) => None,
+            AddInfo::New(dated_ref) => Some(&dated_ref.date),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum Entry<'a> {
-    Updated(&'a str, UpdateInfo<'a>),
-    Added(AddInfo<'a>),
+    Updated(Cow<'a, str>, UpdateInfo<'a>),
+    Added(Cow<'a, str>, AddInfo<'a>),
 }
 
 impl<'a> Entry<'a> {
+    /// The `/`-separated input path this entry was reported under, e.g.
+    /// `nihilistic-nvim/rustacean-nvim/gen-luarc/flake-parts`.
+    pub(crate) fn path(&self) -> &str {
+        match self {
+            Entry::Updated(name, _) => name,
+            Entry::Added(name, _) => name,
+        }
+    }
+
+    pub(crate) fn is_add(&self) -> bool {
+        matches!(self, Entry::Added(..))
+    }
+
+    pub(crate) fn flake_ref(&self) -> Option<&FlakeRef<'a>> {
+        match self {
+            Entry::Updated(_, info) => Some(info.to_ref()),
+            Entry::Added(_, add) => add.flake_ref(),
+        }
+    }
+
+    pub(crate) fn date(&self) -> Option<&str> {
+        match self {
+            Entry::Updated(_, info) => Some(info.to_date()),
+            Entry::Added(_, add) => add.date(),
+        }
+    }
+
     pub fn summary(&self) -> String {
         match self {
-            Entry::Updated(name, info) => format!(
-                " - Updated input [`{name}`]({}): [`{}` ➡️ `{}`]({}) <sub>({} to {})<sub/>",
-                info.from.flake_ref.repo_url(),
-                info.from.flake_ref.sha(),
-                info.to.flake_ref.sha(),
-                info.url().unwrap(), // TODO: handle None
-                info.from.date,
-                info.to.date,
-            )
-            .to_string(),
-            Entry::Added(info) => match info {
-                AddInfo::Follows(repo) => format!(" - Added input (follows `{}`)", repo),
-                AddInfo::New(dated_ref) => format!(
-                    " - Added input [`{}`]({}) ({})",
-                    dated_ref.flake_ref.sha(),
-                    dated_ref.flake_ref.repo_url(),
-                    dated_ref.date
-                ),
+            Entry::Updated(name, info) => {
+                let dir = dir_suffix(&info.to.flake_ref);
+                let days = day_delta(&info.from.date, &info.to.date)
+                    .map(|days| {
+                        if days >= 0 {
+                            format!(" (+{days} days)")
+                        } else {
+                            format!(" ({days} days)")
+                        }
+                    })
+                    .unwrap_or_default();
+                match info.url() {
+                    Some(url) => format!(
+                        " - Updated input [`{name}`]({}): [`{}` ➡️ `{}`]({}) <sub>({} to {}){days}<sub/>{dir}",
+                        info.from.flake_ref.repo_url().unwrap_or_default(),
+                        info.from.flake_ref.sha(),
+                        info.to.flake_ref.sha(),
+                        url,
+                        info.from.date,
+                        info.to.date,
+                    ),
+                    None => format!(
+                        " - Updated input `{name}`: `{}` ➡️ `{}` <sub>({} to {}){days}<sub/>{dir}",
+                        info.from.flake_ref.sha(),
+                        info.to.flake_ref.sha(),
+                        info.from.date,
+                        info.to.date,
+                    ),
+                }
+            }
+            Entry::Added(name, info) => match info {
+                AddInfo::Follows(repo) => format!(" - Added input `{name}` (follows `{}`)", repo),
+                AddInfo::New(dated_ref) => {
+                    let dir = dir_suffix(&dated_ref.flake_ref);
+                    match dated_ref.flake_ref.repo_url() {
+                        Some(url) => format!(
+                            " - Added input [`{name}`]({}): `{}` ({}){dir}",
+                            url,
+                            dated_ref.flake_ref.sha(),
+                            dated_ref.date
+                        ),
+                        None => format!(
+                            " - Added input `{name}`: `{}` ({}){dir}",
+                            dated_ref.flake_ref.sha(),
+                            dated_ref.date
+                        ),
+                    }
+                }
             },
         }
     }
 }
 
+/// Days elapsed between two `YYYY-MM-DD` dates, or `None` if either is
+/// malformed (in which case callers should just omit the delta).
+fn day_delta(from: &str, to: &str) -> Option<i64> {
+    let (fy, fm, fd) = parse_date(from)?;
+    let (ty, tm, td) = parse_date(to)?;
+    Some(days_from_civil(ty, tm, td) - days_from_civil(fy, fm, fd))
+}
+
+/// Rendered suffix pointing at a flake's subdirectory, e.g. ` (in \`nix\`)`, or
+/// empty when the ref doesn't carry a `?dir=`.
+fn dir_suffix(flake_ref: &FlakeRef) -> String {
+    match flake_ref.dir() {
+        Some(dir) => format!(" (in `{dir}`)"),
+        None => String::new(),
+    }
+}
+
 pub fn parse_header(input: &str) -> IResult<&str, ()> {
     let (input, _) = tag("Flake lock file updates:")(input)?;
     let (input, _) = line_ending(input)?;
@@ -199,16 +573,16 @@ fn parse_updated(input: &str) -> IResult<&str, Entry<'_>> {
     let (input, _) = tag("':")(input)?;
     let (input, _) = line_ending(input)?;
     let (input, info) = UpdateInfo::parse_from.parse(input)?;
-    Ok((input, Entry::Updated(package, info)))
+    Ok((input, Entry::Updated(Cow::Borrowed(package), info)))
 }
 
 fn parse_added(input: &str) -> IResult<&str, Entry<'_>> {
     let (input, _) = tag("• Added input '")(input)?;
-    let (input, _) = take_until("':")(input)?;
+    let (input, name) = take_until("':")(input)?;
     let (input, _) = tag("':")(input)?;
     let (input, _) = line_ending(input)?;
     let (input, info) = AddInfo::parse_from.parse(input)?;
-    Ok((input, Entry::Added(info)))
+    Ok((input, Entry::Added(Cow::Borrowed(name), info)))
 }
 
 pub fn parse_entry(input: &str) -> IResult<&str, Entry<'_>> {
@@ -227,11 +601,13 @@ mod tests {
 
         assert_eq!(
             result.1,
-            FlakeRef {
-                ref_type: FlakeRefType::Github,
-                repo: "nix-community/home-manager",
+            FlakeRef::Github(ForgeRef {
+                owner: "nix-community",
+                repo: "home-manager",
                 commit: "bd92e8ee4a6031ca3dd836c91dc41c13fca1e533",
-            }
+                host: None,
+                dir: None,
+            })
         );
     }
 
@@ -243,14 +619,156 @@ mod tests {
 
         assert_eq!(
             result.1,
-            FlakeRef {
-                ref_type: FlakeRefType::Github,
-                repo: "nix-community/home-manager",
+            FlakeRef::Github(ForgeRef {
+                owner: "nix-community",
+                repo: "home-manager",
                 commit: "bd92e8ee4a6031ca3dd836c91dc41c13fca1e533",
-            }
+                host: None,
+                dir: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_flake_ref_with_host_and_dir() {
+        let input = "github:nix-community/home-manager/bd92e8ee4a6031ca3dd836c91dc41c13fca1e533?host=git.example.com&dir=modules";
+        let result = FlakeRef::parse_from(input).expect("parseable flake ref");
+
+        assert_eq!(
+            result.1,
+            FlakeRef::Github(ForgeRef {
+                owner: "nix-community",
+                repo: "home-manager",
+                commit: "bd92e8ee4a6031ca3dd836c91dc41c13fca1e533",
+                host: Some("git.example.com"),
+                dir: Some("modules"),
+            })
+        );
+        assert_eq!(
+            result.1.repo_url().unwrap(),
+            "https://git.example.com/nix-community/home-manager"
+        );
+    }
+
+    #[test]
+    fn test_parse_sourcehut_flake_ref() {
+        let input = "sourcehut:~emersion/wlroots/abc1234";
+        let result = FlakeRef::parse_from(input).expect("parseable flake ref");
+
+        assert_eq!(
+            result.1,
+            FlakeRef::Sourcehut(ForgeRef {
+                owner: "~emersion",
+                repo: "wlroots",
+                commit: "abc1234",
+                host: None,
+                dir: None,
+            })
         );
     }
 
+    #[test]
+    fn test_parse_git_and_path_and_indirect_flake_refs() {
+        assert_eq!(
+            FlakeRef::parse_from("git+https://example.com/foo.git")
+                .expect("parseable git ref")
+                .1,
+            FlakeRef::Git(UrlRef {
+                url: "https://example.com/foo.git",
+                rev: None,
+                dir: None,
+            })
+        );
+        assert_eq!(
+            FlakeRef::parse_from("tarball+https://example.com/foo.tar.gz")
+                .expect("parseable tarball ref")
+                .1,
+            FlakeRef::Tarball(UrlRef {
+                url: "https://example.com/foo.tar.gz",
+                rev: None,
+                dir: None,
+            })
+        );
+        assert_eq!(
+            FlakeRef::parse_from("path:/home/user/foo")
+                .expect("parseable path ref")
+                .1,
+            FlakeRef::Path(PathRef {
+                path: "/home/user/foo",
+                dir: None,
+            })
+        );
+        assert_eq!(
+            FlakeRef::parse_from("nixpkgs").expect("parseable indirect ref").1,
+            FlakeRef::Indirect(IndirectRef {
+                id: "nixpkgs",
+                dir: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_git_ref_with_rev_shows_a_sha_instead_of_the_url() {
+        let input = "git+https://example.com/foo.git?rev=abcdef1234567890";
+        let result = FlakeRef::parse_from(input).expect("parseable git ref");
+
+        assert_eq!(
+            result.1,
+            FlakeRef::Git(UrlRef {
+                url: "https://example.com/foo.git",
+                rev: Some("abcdef1234567890"),
+                dir: None,
+            })
+        );
+        assert_eq!(result.1.sha(), "abcdef12");
+    }
+
+    #[test]
+    fn test_entry_summary_degrades_without_compare_url() {
+        let input = r#"Flake lock file updates:
+
+• Updated input 'devenv':
+    'path:/home/user/devenv' (2025-10-03)
+  → 'path:/home/user/devenv-new' (2025-10-10)
+"#;
+        let remaining = parse_header(input).expect("Failed to parse header").0;
+        let (_, entries) = many0(parse_entry)
+            .parse(remaining)
+            .expect("Failed to parse entries");
+
+        assert_eq!(entries.len(), 1);
+        let summary = entries[0].summary();
+        assert!(!summary.contains('['), "should not emit a markdown link");
+        assert!(summary.contains("/home/user/devenv"));
+        assert!(summary.contains("/home/user/devenv-new"));
+        assert!(summary.contains("(+7 days)"));
+    }
+
+    #[test]
+    fn test_entry_summary_formats_negative_delta_without_a_double_sign() {
+        let input = r#"Flake lock file updates:
+
+• Updated input 'devenv':
+    'path:/home/user/devenv' (2025-10-10)
+  → 'path:/home/user/devenv-new' (2025-10-03)
+"#;
+        let remaining = parse_header(input).expect("Failed to parse header").0;
+        let (_, entries) = many0(parse_entry)
+            .parse(remaining)
+            .expect("Failed to parse entries");
+
+        let summary = entries[0].summary();
+        assert!(summary.contains("(-7 days)"));
+        assert!(!summary.contains("+-"));
+    }
+
+    #[test]
+    fn test_day_delta() {
+        assert_eq!(day_delta("2025-10-03", "2025-10-10"), Some(7));
+        assert_eq!(day_delta("2025-10-10", "2025-10-03"), Some(-7));
+        assert_eq!(day_delta("not-a-date", "2025-10-10"), None);
+    }
+
     #[test]
     fn test_parse_full_input() {
         let input = r#"Flake lock file updates:
@@ -285,41 +803,45 @@ mod tests {
 
         match &entries[0] {
             Entry::Updated(name, info) => {
-                assert_eq!(*name, "home-manager");
-                assert_eq!(info.from.flake_ref.ref_type, FlakeRefType::Github);
-                assert_eq!(info.from.flake_ref.repo, "nix-community/home-manager");
-                assert_eq!(
-                    info.from.flake_ref.commit,
-                    "bd92e8ee4a6031ca3dd836c91dc41c13fca1e533"
-                );
+                assert_eq!(name.as_ref(), "home-manager");
+                match (&info.from.flake_ref, &info.to.flake_ref) {
+                    (FlakeRef::Github(from), FlakeRef::Github(to)) => {
+                        assert_eq!(from.owner, "nix-community");
+                        assert_eq!(from.repo, "home-manager");
+                        assert_eq!(from.commit, "bd92e8ee4a6031ca3dd836c91dc41c13fca1e533");
+                        assert_eq!(to.commit, "bcccb01d0a353c028cc8cb3254cac7ebae32929e");
+                    }
+                    _ => panic!("Expected Github refs"),
+                }
                 assert_eq!(info.from.date, "2025-10-03");
-                assert_eq!(info.to.flake_ref.ref_type, FlakeRefType::Github);
-                assert_eq!(info.to.flake_ref.repo, "nix-community/home-manager");
-                assert_eq!(
-                    info.to.flake_ref.commit,
-                    "bcccb01d0a353c028cc8cb3254cac7ebae32929e"
-                );
                 assert_eq!(info.to.date, "2025-10-10");
             }
             _ => panic!("Expected Updated entry"),
         }
 
         match &entries[3] {
-            Entry::Added(AddInfo::New(info)) => {
-                assert_eq!(info.flake_ref.ref_type, FlakeRefType::Github);
-                assert_eq!(info.flake_ref.repo, "numtide/flake-utils");
-                assert_eq!(
-                    info.flake_ref.commit,
-                    "11707dc2f618dd54ca8739b309ec4fc024de578b"
-                );
-                assert_eq!(info.date, "2024-11-13");
+            Entry::Added(name, AddInfo::New(info)) => {
+                assert_eq!(name.as_ref(), "ltstatus/flake-utils");
+                match &info.flake_ref {
+                    FlakeRef::Github(f) => {
+                        assert_eq!(f.owner, "numtide");
+                        assert_eq!(f.repo, "flake-utils");
+                        assert_eq!(f.commit, "11707dc2f618dd54ca8739b309ec4fc024de578b");
+                        assert_eq!(info.date, "2024-11-13");
+                    }
+                    _ => panic!("Expected Github ref"),
+                }
             }
             _ => panic!("Expected Added entry with New"),
         }
 
         match &entries.last().unwrap() {
-            Entry::Added(AddInfo::Follows(repo)) => {
-                assert_eq!(*repo, "nihilistic-nvim/rustacean-nvim/flake-parts");
+            Entry::Added(name, AddInfo::Follows(repo)) => {
+                assert_eq!(
+                    name.as_ref(),
+                    "nihilistic-nvim/rustacean-nvim/gen-luarc/flake-parts"
+                );
+                assert_eq!(repo.as_ref(), "nihilistic-nvim/rustacean-nvim/flake-parts");
             }
             _ => panic!("Expected Added entry with Follows"),
         }
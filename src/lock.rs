@@ -0,0 +1,297 @@
+//! Parsing and diffing of `flake.lock` files, as an alternative to scraping
+//! `nix flake update`'s text output.
+
+use crate::{AddInfo, DatedFlakeRef, Entry, FlakeRef, ForgeRef, IndirectRef, PathRef, UpdateInfo, UrlRef};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// A parsed `flake.lock` file.
+#[derive(Debug, Deserialize)]
+pub struct LockFile {
+    #[allow(dead_code)]
+    version: u64,
+    root: String,
+    nodes: HashMap<String, Node>,
+}
+
+impl LockFile {
+    pub fn parse(input: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(input)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Node {
+    locked: Option<LockedRef>,
+    #[serde(default)]
+    inputs: HashMap<String, InputRef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockedRef {
+    #[serde(rename = "type")]
+    ref_type: String,
+    owner: Option<String>,
+    repo: Option<String>,
+    rev: Option<String>,
+    url: Option<String>,
+    path: Option<String>,
+    id: Option<String>,
+    #[serde(rename = "lastModified")]
+    last_modified: Option<i64>,
+}
+
+/// The value of a node's `inputs` entry: either the name of another node in
+/// `nodes`, or a path that "follows" an input reachable from the root.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum InputRef {
+    #[allow(dead_code)]
+    Name(String),
+    Follows(Vec<String>),
+}
+
+/// Compare an old and a new lock file and produce the same [`Entry`] values
+/// the text-output parser would, so callers can render one markdown summary
+/// regardless of where the data came from.
+pub fn diff<'a>(old: &'a LockFile, new: &'a LockFile) -> Vec<Entry<'a>> {
+    let mut entries = Vec::new();
+
+    for (name, node) in &new.nodes {
+        if name != &new.root {
+            match old.nodes.get(name) {
+                Some(old_node) => {
+                    if let Some(old_locked) = &old_node.locked
+                        && let Some(new_locked) = &node.locked
+                        && old_locked.rev != new_locked.rev
+                        && let Some(from) = flake_ref_from_locked(old_locked)
+                        && let Some(to) = flake_ref_from_locked(new_locked)
+                    {
+                        let from = DatedFlakeRef::new(from, date_from_locked(old_locked));
+                        let to = DatedFlakeRef::new(to, date_from_locked(new_locked));
+                        entries.push(Entry::Updated(Cow::Borrowed(name.as_str()), UpdateInfo::new(from, to)));
+                    }
+                }
+                None => {
+                    if let Some(locked) = &node.locked
+                        && let Some(flake_ref) = flake_ref_from_locked(locked)
+                    {
+                        let dated = DatedFlakeRef::new(flake_ref, date_from_locked(locked));
+                        entries.push(Entry::Added(Cow::Borrowed(name.as_str()), AddInfo::New(dated)));
+                    }
+                }
+            }
+        }
+
+        // The owning node's key in `nodes` (nothing for the root) is prefixed
+        // onto each new `follows` input, e.g. `pkg/flake-utils`, so that
+        // follows of the same input name on different nodes don't collide in
+        // `render_tree`. The prefix is the node's key, not its full input path
+        // from the root, so a transitive node's follows are not named like the
+        // text output's `parent/.../input`.
+        for (input_name, input) in &node.inputs {
+            let InputRef::Follows(path) = input else {
+                continue;
+            };
+            let was_followed = old
+                .nodes
+                .get(name)
+                .and_then(|old_node| old_node.inputs.get(input_name))
+                .is_some_and(|old_input| matches!(old_input, InputRef::Follows(p) if p == path));
+            if was_followed {
+                continue;
+            }
+            let follows_path = if name == &new.root {
+                input_name.clone()
+            } else {
+                format!("{name}/{input_name}")
+            };
+            entries.push(Entry::Added(
+                Cow::Owned(follows_path),
+                AddInfo::Follows(Cow::Owned(path.join("/"))),
+            ));
+        }
+    }
+
+    entries
+}
+
+fn flake_ref_from_locked(locked: &LockedRef) -> Option<FlakeRef<'_>> {
+    match locked.ref_type.as_str() {
+        "github" => Some(FlakeRef::Github(ForgeRef::new(
+            locked.owner.as_deref()?,
+            locked.repo.as_deref()?,
+            locked.rev.as_deref()?,
+        ))),
+        "gitlab" => Some(FlakeRef::Gitlab(ForgeRef::new(
+            locked.owner.as_deref()?,
+            locked.repo.as_deref()?,
+            locked.rev.as_deref()?,
+        ))),
+        "sourcehut" => Some(FlakeRef::Sourcehut(ForgeRef::new(
+            locked.owner.as_deref()?,
+            locked.repo.as_deref()?,
+            locked.rev.as_deref()?,
+        ))),
+        "git" => Some(FlakeRef::Git(url_ref_from_locked(locked)?)),
+        "tarball" => Some(FlakeRef::Tarball(url_ref_from_locked(locked)?)),
+        "path" => Some(FlakeRef::Path(PathRef::new(locked.path.as_deref()?))),
+        "indirect" => Some(FlakeRef::Indirect(IndirectRef::new(locked.id.as_deref()?))),
+        _ => None,
+    }
+}
+
+fn url_ref_from_locked(locked: &LockedRef) -> Option<UrlRef<'_>> {
+    let url = locked.url.as_deref()?;
+    Some(match locked.rev.as_deref() {
+        Some(rev) => UrlRef::with_rev(url, rev),
+        None => UrlRef::new(url),
+    })
+}
+
+fn date_from_locked(locked: &LockedRef) -> Cow<'static, str> {
+    match locked.last_modified {
+        Some(seconds) => Cow::Owned(date_from_unix(seconds)),
+        None => Cow::Borrowed(""),
+    }
+}
+
+/// Civil date (as `YYYY-MM-DD`) for a unix timestamp, without pulling in a
+/// date/time crate. The inverse of the days-from-civil algorithm.
+fn date_from_unix(seconds: i64) -> String {
+    let days = seconds.div_euclid(86400);
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097; // [0, 146096]
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365; // [0, 399]
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100); // [0, 365]
+    let mp = (5 * doy + 2) / 153; // [0, 11]
+    let d = doy - (153 * mp + 2) / 5 + 1; // [1, 31]
+    let m = if mp < 10 { mp + 3 } else { mp - 9 }; // [1, 12]
+    let y = if m <= 2 { y + 1 } else { y };
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_from_unix() {
+        assert_eq!(date_from_unix(1_728_000_000), "2024-10-04");
+    }
+
+    #[test]
+    fn test_diff_detects_update_add_and_follows() {
+        let old = LockFile::parse(
+            r#"{
+                "version": 7,
+                "root": "root",
+                "nodes": {
+                    "root": { "inputs": { "nixpkgs": "nixpkgs" } },
+                    "nixpkgs": {
+                        "locked": {
+                            "type": "github",
+                            "owner": "nixos",
+                            "repo": "nixpkgs",
+                            "rev": "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+                            "lastModified": 1700000000
+                        }
+                    }
+                }
+            }"#,
+        )
+        .expect("parseable old lock file");
+
+        let new = LockFile::parse(
+            r#"{
+                "version": 7,
+                "root": "root",
+                "nodes": {
+                    "root": { "inputs": { "nixpkgs": "nixpkgs", "flake-utils": "flake-utils" } },
+                    "nixpkgs": {
+                        "locked": {
+                            "type": "github",
+                            "owner": "nixos",
+                            "repo": "nixpkgs",
+                            "rev": "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+                            "lastModified": 1728000000
+                        }
+                    },
+                    "flake-utils": {
+                        "inputs": { "flake-parts": ["nixpkgs"] }
+                    }
+                }
+            }"#,
+        )
+        .expect("parseable new lock file");
+
+        let entries = diff(&old, &new);
+        assert_eq!(entries.len(), 2);
+
+        assert!(
+            entries
+                .iter()
+                .any(|e| matches!(e, Entry::Updated(name, _) if name.as_ref() == "nixpkgs"))
+        );
+        assert!(entries.iter().any(|e| matches!(
+            e,
+            Entry::Added(_, AddInfo::Follows(path)) if path.as_ref() == "nixpkgs"
+        )));
+    }
+
+    #[test]
+    fn test_diff_keys_follows_under_their_owning_node_and_scans_root() {
+        let old = LockFile::parse(
+            r#"{
+                "version": 7,
+                "root": "root",
+                "nodes": {
+                    "root": { "inputs": { "nixpkgs": "nixpkgs", "pkg": "pkg" } },
+                    "nixpkgs": { "locked": { "type": "indirect", "id": "nixpkgs" } },
+                    "pkg": { "inputs": { "nixpkgs": ["nixpkgs"] } }
+                }
+            }"#,
+        )
+        .expect("parseable old lock file");
+
+        let new = LockFile::parse(
+            r#"{
+                "version": 7,
+                "root": "root",
+                "nodes": {
+                    "root": {
+                        "inputs": { "nixpkgs": "nixpkgs", "pkg": "pkg", "extra": ["nixpkgs"] }
+                    },
+                    "nixpkgs": { "locked": { "type": "indirect", "id": "nixpkgs" } },
+                    "pkg": {
+                        "inputs": {
+                            "nixpkgs": ["nixpkgs"],
+                            "flake-utils": ["pkg", "nixpkgs"]
+                        }
+                    }
+                }
+            }"#,
+        )
+        .expect("parseable new lock file");
+
+        let entries = diff(&old, &new);
+        let follows_paths: Vec<&str> = entries
+            .iter()
+            .filter_map(|e| match e {
+                Entry::Added(name, AddInfo::Follows(_)) => Some(name.as_ref()),
+                _ => None,
+            })
+            .collect();
+
+        // the node's own `flake-utils` follows is keyed under the node's name,
+        // not just the bare input name, so it doesn't collide with other entries
+        assert!(follows_paths.contains(&"pkg/flake-utils"));
+        // a new follows declared directly on the root has no node name to
+        // prefix and is still reported
+        assert!(follows_paths.contains(&"extra"));
+        assert_eq!(follows_paths.len(), 2);
+    }
+}
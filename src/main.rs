@@ -1,37 +1,134 @@
-use flakers::{parse_entry, parse_header};
+use flakers::{Condition, Entry, LockFile, diff_lock_files, parse_entry, parse_header, render_tree};
 use nom::{Parser, multi::many0};
 use std::io::{self, Read};
 
 fn main() {
-    let mut input = String::new();
-    io::stdin()
-        .read_to_string(&mut input)
-        .expect("Failed to read stdin");
-
-    let remaining = match parse_header(&input) {
-        Ok((remaining, _)) => remaining,
-        Err(e) => {
-            eprintln!("Failed to parse header: {}", e);
-            std::process::exit(1);
+    let args = parse_args();
+
+    match &args.lock_files {
+        Some((from_path, to_path)) => {
+            let from = read_lock_file(from_path);
+            let to = read_lock_file(to_path);
+            print_entries(diff_lock_files(&from, &to), &args.condition);
         }
-    };
+        None => {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .expect("Failed to read stdin");
+
+            let remaining = match parse_header(&input) {
+                Ok((remaining, _)) => remaining,
+                Err(e) => {
+                    eprintln!("Failed to parse header: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            let entries = match many0(parse_entry).parse(remaining) {
+                Ok((_, entries)) => entries,
+                Err(e) => {
+                    eprintln!("Failed to parse entries: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            println!("<details><summary>Raw output</summary><p>");
+            println!("\n```");
+            print!("{}", input);
+            println!("```");
+            println!("\n</p></details>\n");
+
+            print_entries(entries, &args.condition);
+        }
+    }
+}
+
+fn print_entries(entries: Vec<Entry<'_>>, condition: &Option<Condition>) {
+    let entries = entries
+        .into_iter()
+        .filter(|entry| match condition {
+            None => true,
+            Some(condition) => condition.matches(entry).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }),
+        })
+        .collect::<Vec<_>>();
 
-    let entries = match many0(parse_entry).parse(remaining) {
-        Ok((_, entries)) => entries,
-        Err(e) => {
-            eprintln!("Failed to parse entries: {}", e);
+    print!("{}", render_tree(&entries));
+}
+
+fn read_lock_file(path: &str) -> LockFile {
+    let input = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("failed to read {path}: {e}");
+        std::process::exit(1);
+    });
+    LockFile::parse(&input).unwrap_or_else(|e| {
+        eprintln!("failed to parse {path}: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Parsed argv: an optional `--condition <expr>` and an optional
+/// `--from-lock <path> --to-lock <path>` pair. When the lock-file pair is
+/// given, entries come from diffing those two `flake.lock` files instead of
+/// the text output read from stdin.
+struct Args {
+    condition: Option<Condition>,
+    lock_files: Option<(String, String)>,
+}
+
+fn parse_args() -> Args {
+    let mut args = std::env::args().skip(1);
+    let mut expr = None;
+    let mut from_lock = None;
+    let mut to_lock = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--condition" => {
+                expr = Some(args.next().unwrap_or_else(|| {
+                    eprintln!("--condition requires an expression argument");
+                    std::process::exit(1);
+                }));
+            }
+            "--from-lock" => {
+                from_lock = Some(args.next().unwrap_or_else(|| {
+                    eprintln!("--from-lock requires a path argument");
+                    std::process::exit(1);
+                }));
+            }
+            "--to-lock" => {
+                to_lock = Some(args.next().unwrap_or_else(|| {
+                    eprintln!("--to-lock requires a path argument");
+                    std::process::exit(1);
+                }));
+            }
+            other => {
+                eprintln!("unknown argument: {other}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let lock_files = match (from_lock, to_lock) {
+        (Some(from), Some(to)) => Some((from, to)),
+        (None, None) => None,
+        _ => {
+            eprintln!("--from-lock and --to-lock must be given together");
             std::process::exit(1);
         }
     };
 
-    println!("<details><summary>Raw output</summary><p>");
-    println!("\n```");
-    print!("{}", input);
-    println!("```");
-    println!("\n</p></details>\n");
+    let condition = expr.map(|expr| {
+        Condition::compile(&expr).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        })
+    });
 
-    // TODO sort and list added first
-    for entry in &entries {
-        println!("{}", entry.summary());
+    Args {
+        condition,
+        lock_files,
     }
 }
@@ -0,0 +1,102 @@
+//! Groups flat [`Entry`] values into a tree keyed on their `/`-separated
+//! input path, so deeply nested inputs (`devenv/cachix/devenv/nix/nixpkgs`)
+//! fold under their top-level parent instead of listing flat.
+
+use crate::Entry;
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+struct Node<'a> {
+    /// This entry's own rendered line, if the path ends exactly here.
+    line: Option<String>,
+    /// Whether this node or anything below it is an addition; used to sort
+    /// top-level entries with additions first.
+    has_addition: bool,
+    children: BTreeMap<&'a str, Node<'a>>,
+}
+
+fn insert<'a>(node: &mut Node<'a>, path: &[&'a str], line: String, is_add: bool) {
+    node.has_addition |= is_add;
+    match path {
+        [] => node.line = Some(line),
+        [head, tail @ ..] => insert(node.children.entry(head).or_default(), tail, line, is_add),
+    }
+}
+
+fn render_node(name: &str, node: &Node, out: &mut String) {
+    if node.children.is_empty() {
+        if let Some(line) = &node.line {
+            out.push_str(line);
+            out.push('\n');
+        }
+        return;
+    }
+
+    let summary = node
+        .line
+        .clone()
+        .unwrap_or_else(|| format!(" - `{name}`"));
+    out.push_str(&format!("<details><summary>{summary}</summary>\n\n"));
+    for (child_name, child_node) in &node.children {
+        render_node(child_name, child_node, out);
+    }
+    out.push_str("\n</details>\n");
+}
+
+/// Render entries as a nested markdown list: top-level inputs are sorted with
+/// additions first, and inputs nested under another (e.g. a transitive
+/// `follows`) are folded into a `<details>` block under their parent.
+pub fn render(entries: &[Entry]) -> String {
+    let mut root = Node::default();
+    for entry in entries {
+        let path: Vec<&str> = entry.path().split('/').collect();
+        insert(&mut root, &path, entry.summary(), entry.is_add());
+    }
+
+    let mut top_level: Vec<_> = root.children.into_iter().collect();
+    top_level.sort_by(|(a_name, a_node), (b_name, b_node)| {
+        b_node
+            .has_addition
+            .cmp(&a_node.has_addition)
+            .then_with(|| a_name.cmp(b_name))
+    });
+
+    let mut out = String::new();
+    for (name, node) in &top_level {
+        render_node(name, node, &mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_entry, parse_header};
+    use nom::{Parser, multi::many0};
+
+    #[test]
+    fn test_render_nests_transitive_inputs_and_sorts_additions_first() {
+        let input = r#"Flake lock file updates:
+
+• Updated input 'nixpkgs':
+    'github:nixos/nixpkgs/aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa' (2025-10-02)
+  → 'github:nixos/nixpkgs/bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb' (2025-10-09)
+• Added input 'devenv/cachix/devenv/nix/nixpkgs':
+    'github:nixos/nixpkgs/cccccccccccccccccccccccccccccccccccccccc' (2025-10-09)
+"#;
+        let remaining = parse_header(input).expect("Failed to parse header").0;
+        let (_, entries) = many0(parse_entry)
+            .parse(remaining)
+            .expect("Failed to parse entries");
+
+        let rendered = render(&entries);
+
+        // additions are sorted before updates at the top level
+        let devenv_pos = rendered.find("devenv").unwrap();
+        let nixpkgs_pos = rendered.find("Updated input").unwrap();
+        assert!(devenv_pos < nixpkgs_pos);
+
+        // the transitive input is folded under its top-level parent
+        assert!(rendered.contains("<details><summary> - `devenv`</summary>"));
+    }
+}